@@ -114,6 +114,38 @@
 //! nested model into it's own independent model, view, update. Nevertheless,
 //! sometimes this isn't desired or worth it, so the option is there in case
 //! you need it.
+//!
+//! A bare `#[model]` field, as above, is nested manually: you read
+//! and update it like any other field, and [`Cmd::child`] is there
+//! if you want to forward its messages through your own `Msg::Child`
+//! variant by hand.
+//!
+//! If instead you annotate the field with the child's `Msg` type,
+//! the variant that carries it in the parent's `Msg`, and the
+//! child's `update` function, `Model` generates the routing for you:
+//!
+//! ```rust,ignore
+//! #[derive(leptos_tea::Model)]
+//! #[model(msg = Msg)]
+//! struct Model {
+//!   #[model(msg = ChildMsg, variant = Child, update = child_update)]
+//!   inner_model: InnerModel,
+//! }
+//!
+//! enum Msg {
+//!   Child(ChildMsg),
+//!   // ...
+//! }
+//! ```
+//!
+//! This generates an `update` wrapper that checks incoming messages
+//! against `Msg::Child` first: a match routes the inner `ChildMsg`
+//! straight to `child_update`, using [`Cmd::child`] to re-wrap
+//! anything the child dispatches or queues back into `Msg::Child`,
+//! and only falls through to your own `update` function otherwise.
+//! The struct-level `#[model(msg = Msg)]` is required whenever any
+//! field uses this form, since the generated wrapper has to know the
+//! concrete `Msg` type to match on.
 
 //!
 //! # Limitations
@@ -121,16 +153,90 @@
 //! `leptos_tea::Model` currently only supports tuple and field structs.
 //! Support will be added soon.
 
-use futures::FutureExt;
+use futures::{
+  channel::mpsc,
+  future::{
+    abortable,
+    AbortHandle,
+  },
+  stream::{
+    Abortable,
+    Stream,
+  },
+  FutureExt,
+  StreamExt,
+};
 use leptos_reactive::*;
 pub use leptos_tea_macros::*;
 use smallvec::SmallVec;
 use std::{
+  cell::{
+    Cell,
+    RefCell,
+  },
+  collections::HashMap,
   future::Future,
+  hash::Hash,
   pin::Pin,
+  rc::Rc,
 };
 
 type CmdFut<Msg> = Pin<Box<dyn Future<Output = SmallVec<[Msg; 4]>>>>;
+type SubStream<Msg> = Pin<Box<dyn Stream<Item = Msg>>>;
+
+/// Shared bookkeeping for every [`Cmd`] built over a single
+/// component's lifetime.
+///
+/// `Cmd` mirrors Elm's `update : Msg -> Model -> (Model, Cmd Msg)`:
+/// a fresh one is built per dispatched message, by calling
+/// [`Cmd::new`] with the same `cx` every time. Subscriptions started
+/// by any of those `Cmd`s still need to be torn down together,
+/// exactly once, when the component's [`Scope`] is disposed, so
+/// `Cmd::new` keeps one `CmdLifecycle` per `cx` in a thread-local
+/// cache (evicted on disposal) rather than registering a fresh
+/// `on_cleanup` callback on every call.
+#[derive(Clone)]
+struct CmdLifecycle(Rc<RefCell<Vec<AbortHandle>>>);
+
+impl CmdLifecycle {
+  /// Returns the `CmdLifecycle` for `cx`, creating it (and
+  /// registering the single `on_cleanup` callback that aborts every
+  /// subscription registered against it, then evicts it from the
+  /// cache) the first time this `cx` is seen.
+  fn for_scope(cx: Scope) -> Self {
+    thread_local! {
+      static LIFECYCLES: RefCell<HashMap<Scope, CmdLifecycle>> =
+        RefCell::new(HashMap::new());
+    }
+
+    LIFECYCLES.with(|lifecycles| {
+      lifecycles
+        .borrow_mut()
+        .entry(cx)
+        .or_insert_with(|| {
+          let abort_handles: Rc<RefCell<Vec<AbortHandle>>> = Default::default();
+
+          let on_dispose = Rc::clone(&abort_handles);
+          on_cleanup(cx, move || {
+            for handle in on_dispose.borrow_mut().drain(..) {
+              handle.abort();
+            }
+
+            LIFECYCLES.with(|lifecycles| {
+              lifecycles.borrow_mut().remove(&cx);
+            });
+          });
+
+          Self(abort_handles)
+        })
+        .clone()
+    })
+  }
+
+  fn register(&self, handle: AbortHandle) {
+    self.0.borrow_mut().push(handle);
+  }
+}
 
 /// Command manager that allows dispatching messages and running
 /// asynchronous operations.
@@ -138,6 +244,8 @@ pub struct Cmd<Msg: 'static> {
   msg_dispatcher: SignalSetter<Msg>,
   msgs: SmallVec<[Msg; 4]>,
   cmds: SmallVec<[CmdFut<Msg>; 4]>,
+  subs: SmallVec<[SubStream<Msg>; 4]>,
+  lifecycle: CmdLifecycle,
 }
 
 impl<Msg: 'static> Cmd<Msg> {
@@ -145,11 +253,20 @@ impl<Msg: 'static> Cmd<Msg> {
   ///
   /// You shouldn't need to use this, as it will be
   /// code generated by the [`Model`] derive macro.
-  pub fn new(msg_dispatcher: SignalSetter<Msg>) -> Self {
+  ///
+  /// `cx` ties any [`Cmd::sub`] subscriptions to the lifetime of the
+  /// component: when `cx`'s [`Scope`] is disposed, they're aborted
+  /// rather than dispatching into a dead signal. Since a new `Cmd`
+  /// is built per dispatched message, the `on_cleanup` bookkeeping
+  /// behind this is cached per `cx` rather than re-registered every
+  /// call — see `CmdLifecycle`.
+  pub fn new(cx: Scope, msg_dispatcher: SignalSetter<Msg>) -> Self {
     Self {
       msg_dispatcher,
       cmds: Default::default(),
       msgs: Default::default(),
+      subs: Default::default(),
+      lifecycle: CmdLifecycle::for_scope(cx),
     }
   }
 
@@ -184,6 +301,99 @@ impl<Msg: 'static> Cmd<Msg> {
 
     self
   }
+
+  /// Same as [`Cmd::cmd`], but the command can be cancelled before
+  /// it resolves by calling `abort()` on the returned
+  /// [`AbortHandle`].
+  ///
+  /// This is useful for debounced search-as-you-type or superseded
+  /// network requests, where an in-flight command should be
+  /// abandoned once a newer message makes it stale. If the command
+  /// is aborted, it simply never dispatches any of its messages.
+  pub fn abortable_cmd<Fut, I>(&mut self, cmd: Fut) -> AbortHandle
+  where
+    Fut: Future<Output = I> + 'static,
+    I: IntoIterator<Item = Msg>,
+  {
+    let (cmd, handle) = abortable(cmd);
+
+    self.cmds.push(Box::pin(cmd.map(|result| match result {
+      Ok(msgs) => msgs.into_iter().collect(),
+      Err(_aborted) => SmallVec::new(),
+    })));
+
+    handle
+  }
+
+  /// Same as [`Cmd::cmd`], but for fallible commands.
+  ///
+  /// On `Ok`, the produced messages are dispatched as usual. On
+  /// `Err`, `on_err` maps the error into a single [`Msg`] (e.g.
+  /// `Msg::RequestFailed(String)`), which is dispatched instead.
+  /// This makes error handling explicit rather than requiring every
+  /// caller to hand-roll a `match` inside their async closure.
+  pub fn try_cmd<Fut, T, E>(
+    &mut self,
+    cmd: Fut,
+    on_err: impl FnOnce(E) -> Msg + 'static,
+  ) -> &mut Self
+  where
+    Fut: Future<Output = Result<T, E>> + 'static,
+    T: IntoIterator<Item = Msg>,
+  {
+    self.cmds.push(Box::pin(cmd.map(|result| match result {
+      Ok(msgs) => msgs.into_iter().collect(),
+      Err(err) => {
+        let mut msgs = SmallVec::new();
+        msgs.push(on_err(err));
+        msgs
+      }
+    })));
+
+    self
+  }
+
+  /// Creates a [`Cmd`] for a nested model's own `Msg` type, wiring
+  /// it so that anything the child dispatches or queues is
+  /// forwarded back through this [`Cmd`], re-wrapped with `wrap`.
+  ///
+  /// It lets a parent `update` function pass a `#[model]` child's
+  /// own `update` function a `Cmd<ChildMsg>` without the child
+  /// needing to know anything about the parent's `Msg` type. This is
+  /// also the primitive the [`Model`] derive's generated routing
+  /// uses for fields annotated with a `variant`/`update` (see the
+  /// "Model nesting" section of the crate docs); call it yourself
+  /// only for hand-rolled nesting on a bare `#[model]` field.
+  pub fn child<ChildMsg: 'static>(
+    &self,
+    cx: Scope,
+    wrap: impl Fn(ChildMsg) -> Msg + 'static,
+  ) -> Cmd<ChildMsg> {
+    let parent_dispatcher = self.msg_dispatcher;
+
+    let child_dispatcher = SignalSetter::map(cx, move |child_msg| {
+      parent_dispatcher.set(wrap(child_msg));
+    });
+
+    Cmd::new(cx, child_dispatcher)
+  }
+
+  /// Registers a long-running subscription that keeps producing
+  /// messages over time, e.g. an interval ticker, a `WebSocket`
+  /// reader, or any other [`Stream`].
+  ///
+  /// Unlike [`Cmd::cmd`], which runs once and is done, a
+  /// subscription is polled for the lifetime of the component and
+  /// is automatically stopped once its owning [`Scope`] is
+  /// disposed.
+  pub fn sub<S>(&mut self, stream: S) -> &mut Self
+  where
+    S: Stream<Item = Msg> + 'static,
+  {
+    self.subs.push(Box::pin(stream));
+
+    self
+  }
 }
 
 impl<Msg: 'static> Drop for Cmd<Msg> {
@@ -207,6 +417,20 @@ impl<Msg: 'static> Drop for Cmd<Msg> {
     for msg in std::mem::take(&mut self.msgs) {
       queue_microtask(move || msg_dispatcher.set(msg));
     }
+
+    for stream in std::mem::take(&mut self.subs) {
+      let msg_dispatcher = self.msg_dispatcher;
+      let (handle, registration) = AbortHandle::new_pair();
+      self.lifecycle.register(handle);
+
+      let mut stream = Abortable::new(stream, registration);
+
+      spawn_local(async move {
+        while let Some(msg) = stream.next().await {
+          msg_dispatcher(msg);
+        }
+      });
+    }
   }
 }
 
@@ -227,6 +451,20 @@ impl<Msg: 'static> Clone for MsgDispatcher<Msg> {
 
 impl<Msg: 'static> Copy for MsgDispatcher<Msg> {}
 
+impl<Msg: 'static> PartialEq for MsgDispatcher<Msg> {
+  fn eq(&self, other: &Self) -> bool {
+    self.0 == other.0
+  }
+}
+
+impl<Msg: 'static> Eq for MsgDispatcher<Msg> {}
+
+impl<Msg: 'static> std::hash::Hash for MsgDispatcher<Msg> {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.0.hash(state);
+  }
+}
+
 impl<Msg: 'static> SignalSet<Msg> for MsgDispatcher<Msg> {
   fn set(&self, new_value: Msg) {
     self.0.set(new_value);
@@ -270,6 +508,34 @@ impl<Msg> MsgDispatcher<Msg> {
     self.set(msg);
   }
 
+  /// Bridges non-reactive senders into the update loop.
+  ///
+  /// Raw `wasm_bindgen` callbacks, `web_sys` event closures, and
+  /// async tasks that don't hold this dispatcher can't call
+  /// [`MsgDispatcher::dispatch`] directly, since that must happen
+  /// inside the reactive runtime. This returns an
+  /// `UnboundedSender<Msg>` that can be cloned and handed to those
+  /// callback sites instead; every message sent down it is
+  /// forwarded to `self.dispatch` by a task that lives for `cx`'s
+  /// [`Scope`] and stops once the channel is closed or the scope is
+  /// disposed.
+  pub fn channel(self, cx: Scope) -> mpsc::UnboundedSender<Msg> {
+    let (sender, receiver) = mpsc::unbounded();
+
+    let (handle, registration) = AbortHandle::new_pair();
+    on_cleanup(cx, move || handle.abort());
+
+    let mut receiver = Abortable::new(receiver, registration);
+
+    spawn_local(async move {
+      while let Some(msg) = receiver.next().await {
+        self.dispatch(msg);
+      }
+    });
+
+    sender
+  }
+
   /// Queues the message to be sent to the update function on
   /// the next micro-task, instead of sending the message
   /// immediately.
@@ -280,6 +546,65 @@ impl<Msg> MsgDispatcher<Msg> {
     queue_microtask(move || self.dispatch(msg));
   }
 
+  /// "Latest wins" dispatch for messages that arrive faster than
+  /// the update loop should process them, e.g. pointer-move,
+  /// resize, scroll, or slider drag.
+  ///
+  /// Messages sharing the same `key` *on this dispatcher* collapse
+  /// so that only the final value queued within a microtask turn is
+  /// delivered: N rapid calls for the same key produce exactly one
+  /// `update()` call carrying the newest payload, instead of N
+  /// calls. Two different dispatchers (e.g. sibling widgets, or a
+  /// parent/child pair routed through [`Cmd::child`]) using the
+  /// same key never share a slot.
+  pub fn dispatch_latest<K>(self, key: K, msg: Msg)
+  where
+    K: Eq + Hash + Clone + 'static,
+  {
+    type Pending<M, Q> = HashMap<(MsgDispatcher<M>, Q), Rc<Cell<Option<M>>>>;
+
+    thread_local! {
+      static PENDING: RefCell<HashMap<std::any::TypeId, Box<dyn std::any::Any>>> =
+        RefCell::new(HashMap::new());
+    }
+
+    let map_key = (self, key);
+    let type_key = std::any::TypeId::of::<Pending<Msg, K>>();
+
+    let slot = PENDING.with(|pending| {
+      let mut pending = pending.borrow_mut();
+      let pending = pending
+        .entry(type_key)
+        .or_insert_with(|| Box::new(Pending::<Msg, K>::new()))
+        .downcast_mut::<Pending<Msg, K>>()
+        .expect("entry is keyed by its own type");
+
+      Rc::clone(
+        pending
+          .entry(map_key.clone())
+          .or_insert_with(|| Rc::new(Cell::new(None))),
+      )
+    });
+
+    let had_pending = slot.replace(Some(msg)).is_some();
+
+    if !had_pending {
+      queue_microtask(move || {
+        PENDING.with(|pending| {
+          if let Some(pending) = pending.borrow_mut().get_mut(&type_key) {
+            if let Some(pending) = pending.downcast_mut::<Pending<Msg, K>>() {
+              pending.remove(&map_key);
+            }
+          }
+        });
+
+        if let Some(msg) = slot.take() {
+          self.dispatch(msg);
+        }
+      });
+    }
+  }
+
   /// Batches multiple messages together.
   ///
   /// All messages are sent one after another.
@@ -0,0 +1,389 @@
+//! Derive macro backing [`leptos_tea`](https://docs.rs/leptos_tea).
+//!
+//! See the crate-level docs over there for how `#[derive(Model)]` and
+//! its `#[model(..)]` field/struct attributes are meant to be used;
+//! this crate is just the codegen.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{
+  format_ident,
+  quote,
+};
+use syn::{
+  parse::{
+    Parse,
+    ParseStream,
+  },
+  parse_macro_input,
+  punctuated::Punctuated,
+  Data,
+  DeriveInput,
+  Fields,
+  Ident,
+  Path,
+  Token,
+};
+
+/// `#[model(msg = Msg)]` on the struct itself: the parent `Msg` type,
+/// only required when at least one field uses the routed form below.
+struct StructModelArgs {
+  msg: Option<Path>,
+}
+
+impl Parse for StructModelArgs {
+  fn parse(input: ParseStream) -> syn::Result<Self> {
+    let mut msg = None;
+
+    for pair in Punctuated::<syn::MetaNameValue, Token![,]>::parse_terminated(input)? {
+      if pair.path.is_ident("msg") {
+        msg = Some(parse_path_value(&pair)?);
+      } else {
+        return Err(syn::Error::new_spanned(pair.path, "unknown `model` argument, expected `msg`"));
+      }
+    }
+
+    Ok(Self { msg })
+  }
+}
+
+/// `#[model(..)]` on a field. Bare `#[model]` (no args) marks a
+/// hand-nested child model; `#[model(msg = ChildMsg, variant =
+/// Child, update = child_update)]` marks one `Model` should route to
+/// automatically.
+#[derive(Default)]
+struct FieldModelArgs {
+  msg: Option<Path>,
+  variant: Option<Ident>,
+  update: Option<Path>,
+}
+
+impl Parse for FieldModelArgs {
+  fn parse(input: ParseStream) -> syn::Result<Self> {
+    let mut args = Self::default();
+
+    if input.is_empty() {
+      return Ok(args);
+    }
+
+    for pair in Punctuated::<syn::MetaNameValue, Token![,]>::parse_terminated(input)? {
+      if pair.path.is_ident("msg") {
+        args.msg = Some(parse_path_value(&pair)?);
+      } else if pair.path.is_ident("variant") {
+        args.variant = Some(parse_ident_value(&pair)?);
+      } else if pair.path.is_ident("update") {
+        args.update = Some(parse_path_value(&pair)?);
+      } else {
+        return Err(syn::Error::new_spanned(
+          pair.path,
+          "unknown `model` argument, expected one of `msg`, `variant`, `update`",
+        ));
+      }
+    }
+
+    Ok(args)
+  }
+}
+
+fn parse_path_value(pair: &syn::MetaNameValue) -> syn::Result<Path> {
+  match &pair.value {
+    syn::Expr::Path(expr_path) => Ok(expr_path.path.clone()),
+    other => Err(syn::Error::new_spanned(other, "expected a path")),
+  }
+}
+
+fn parse_ident_value(pair: &syn::MetaNameValue) -> syn::Result<Ident> {
+  match &pair.value {
+    syn::Expr::Path(expr_path) if expr_path.path.get_ident().is_some() => {
+      Ok(expr_path.path.get_ident().unwrap().clone())
+    }
+    other => Err(syn::Error::new_spanned(other, "expected a plain identifier")),
+  }
+}
+
+/// How one field of the derived struct is meant to be nested.
+enum ModelField {
+  /// A plain, non-model field: gets an `RwSignal`/`ReadSignal` pair.
+  Plain,
+  /// `#[model]`: a hand-nested child model, wired up by the caller.
+  Nested,
+  /// `#[model(msg = .., variant = .., update = ..)]`: a child model
+  /// this derive routes messages to automatically.
+  Routed { variant: Ident, update: Path },
+}
+
+struct ParsedField {
+  ident: Ident,
+  ty: syn::Type,
+  kind: ModelField,
+}
+
+#[proc_macro_derive(Model, attributes(model))]
+pub fn derive_model(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+
+  match expand(input) {
+    Ok(tokens) => tokens.into(),
+    Err(err) => err.to_compile_error().into(),
+  }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+  let struct_name = input.ident;
+
+  let struct_args = input
+    .attrs
+    .iter()
+    .find(|attr| attr.path().is_ident("model"))
+    .map(|attr| attr.parse_args::<StructModelArgs>())
+    .transpose()?
+    .unwrap_or(StructModelArgs { msg: None });
+
+  let Data::Struct(data) = input.data else {
+    return Err(syn::Error::new(Span::call_site(), "`Model` can only be derived for structs"));
+  };
+
+  let fields = match data.fields {
+    Fields::Named(named) => named
+      .named
+      .into_iter()
+      .map(|field| {
+        let ident = field.ident.expect("named field has an ident");
+        parse_field(ident, field.ty, field.attrs)
+      })
+      .collect::<syn::Result<Vec<_>>>()?,
+    Fields::Unnamed(unnamed) => unnamed
+      .unnamed
+      .into_iter()
+      .enumerate()
+      .map(|(index, field)| {
+        let ident = format_ident!("field{}", index);
+        parse_field(ident, field.ty, field.attrs)
+      })
+      .collect::<syn::Result<Vec<_>>>()?,
+    Fields::Unit => {
+      return Err(syn::Error::new(
+        Span::call_site(),
+        "`Model` currently only supports tuple and field structs",
+      ))
+    }
+  };
+
+  let routed_fields: Vec<&ParsedField> =
+    fields.iter().filter(|field| matches!(field.kind, ModelField::Routed { .. })).collect();
+
+  if !routed_fields.is_empty() && struct_args.msg.is_none() {
+    return Err(syn::Error::new(
+      Span::call_site(),
+      "fields using `#[model(msg = .., variant = .., update = ..)]` require a \
+       struct-level `#[model(msg = Msg)]` naming the parent `Msg` type",
+    ));
+  }
+
+  let update_name = format_ident!("Update{}", struct_name);
+  let view_name = format_ident!("View{}", struct_name);
+
+  let update_struct_fields = fields.iter().map(|field| {
+    let ident = &field.ident;
+    match &field.kind {
+      ModelField::Plain => {
+        let ty = &field.ty;
+        quote! { #ident: ::leptos_reactive::RwSignal<#ty> }
+      }
+      ModelField::Nested | ModelField::Routed { .. } => {
+        let inner_update = format_ident!("Update{}", type_ident(&field.ty));
+        quote! { #ident: #inner_update }
+      }
+    }
+  });
+
+  let view_struct_fields = fields.iter().map(|field| {
+    let ident = &field.ident;
+    match &field.kind {
+      ModelField::Plain => {
+        let ty = &field.ty;
+        quote! { #ident: ::leptos_reactive::ReadSignal<#ty> }
+      }
+      ModelField::Nested | ModelField::Routed { .. } => {
+        let inner_view = format_ident!("View{}", type_ident(&field.ty));
+        quote! { #ident: #inner_view }
+      }
+    }
+  });
+
+  let (signal_bindings, update_ctor_fields, view_ctor_fields) = build_into_signals(&fields);
+
+  let init_impl = if routed_fields.is_empty() {
+    quote! {
+      impl #struct_name {
+        /// Initializes everything and starts listening for messages.
+        /// `Msg::default()` is sent to `update_fn` once immediately.
+        pub fn init<Msg: ::std::default::Default + 'static>(
+          self,
+          cx: ::leptos_reactive::Scope,
+          update_fn: impl Fn(#update_name, &Msg, ::leptos_tea::Cmd<Msg>) + 'static,
+        ) -> (#view_name, ::leptos_reactive::SignalSetter<Msg>) {
+          let (update_model, view_model) = self.into_signals(cx);
+
+          let (msg, set_msg) = ::leptos_reactive::create_signal(cx, Msg::default());
+          let msg_dispatcher =
+            ::leptos_reactive::SignalSetter::map(cx, move |new_msg| set_msg.set(new_msg));
+
+          ::leptos_reactive::create_effect(cx, move |_| {
+            update_fn(update_model, &msg.get(), ::leptos_tea::Cmd::new(cx, msg_dispatcher));
+          });
+
+          (view_model, msg_dispatcher)
+        }
+      }
+    }
+  } else {
+    let struct_msg = struct_args.msg.as_ref().unwrap();
+    let route_arms = routed_fields.iter().map(|field| {
+      let ident = &field.ident;
+      let ModelField::Routed { variant, update } = &field.kind else {
+        unreachable!("routed_fields only contains Routed fields")
+      };
+
+      quote! {
+        #struct_msg::#variant(child_msg) => {
+          #update(update_model.#ident, child_msg, cmd.child(cx, #struct_msg::#variant));
+        }
+      }
+    });
+
+    quote! {
+      impl #struct_name {
+        /// Initializes everything and starts listening for messages.
+        /// `Msg::default()` is sent to `update_fn` once immediately.
+        ///
+        /// Incoming messages are routed to each routed `#[model(..)]`
+        /// field's own `update` function first, wrapping its `Cmd`
+        /// back into this model's `Msg`; anything left over falls
+        /// through to `update_fn`.
+        pub fn init(
+          self,
+          cx: ::leptos_reactive::Scope,
+          update_fn: impl Fn(#update_name, &#struct_msg, ::leptos_tea::Cmd<#struct_msg>) + 'static,
+        ) -> (#view_name, ::leptos_reactive::SignalSetter<#struct_msg>) {
+          let (update_model, view_model) = self.into_signals(cx);
+
+          let (msg, set_msg) = ::leptos_reactive::create_signal(cx, #struct_msg::default());
+          let msg_dispatcher =
+            ::leptos_reactive::SignalSetter::map(cx, move |new_msg| set_msg.set(new_msg));
+
+          ::leptos_reactive::create_effect(cx, move |_| {
+            let msg = msg.get();
+            let cmd = ::leptos_tea::Cmd::new(cx, msg_dispatcher);
+
+            match &msg {
+              #(#route_arms)*
+              _ => update_fn(update_model, &msg, cmd),
+            }
+          });
+
+          (view_model, msg_dispatcher)
+        }
+      }
+    }
+  };
+
+  Ok(quote! {
+    /// Generated by `#[derive(Model)]`; the field-level signals your
+    /// `update` function gets called with.
+    #[derive(Clone, Copy)]
+    pub struct #update_name {
+      #(#update_struct_fields,)*
+    }
+
+    /// Generated by `#[derive(Model)]`; the field-level signals your
+    /// view gets to read from.
+    #[derive(Clone, Copy)]
+    pub struct #view_name {
+      #(#view_struct_fields,)*
+    }
+
+    impl #struct_name {
+      fn into_signals(self, cx: ::leptos_reactive::Scope) -> (#update_name, #view_name) {
+        #(#signal_bindings)*
+
+        (
+          #update_name { #(#update_ctor_fields,)* },
+          #view_name { #(#view_ctor_fields,)* },
+        )
+      }
+    }
+
+    #init_impl
+  })
+}
+
+fn parse_field(ident: Ident, ty: syn::Type, attrs: Vec<syn::Attribute>) -> syn::Result<ParsedField> {
+  let model_attr = attrs.into_iter().find(|attr| attr.path().is_ident("model"));
+
+  let kind = match model_attr {
+    None => ModelField::Plain,
+    Some(attr) => {
+      let args: FieldModelArgs =
+        if matches!(attr.meta, syn::Meta::Path(_)) { FieldModelArgs::default() } else { attr.parse_args()? };
+
+      match (args.msg, args.variant, args.update) {
+        (None, None, None) => ModelField::Nested,
+        (Some(_), Some(variant), Some(update)) => ModelField::Routed { variant, update },
+        _ => {
+          return Err(syn::Error::new(
+            Span::call_site(),
+            "`#[model(..)]` on a field needs either no arguments (hand-nested) or \
+             all three of `msg`, `variant`, and `update` (auto-routed)",
+          ))
+        }
+      }
+    }
+  };
+
+  Ok(ParsedField { ident, ty, kind })
+}
+
+/// The final path segment of a field's type, used to name its
+/// generated `Update*`/`View*` companion types.
+fn type_ident(ty: &syn::Type) -> Ident {
+  match ty {
+    syn::Type::Path(type_path) => {
+      type_path.path.segments.last().expect("type path has at least one segment").ident.clone()
+    }
+    other => panic!("`#[model]` fields must be named types, found {other:?}"),
+  }
+}
+
+/// Builds the `let` bindings for `into_signals` plus the field
+/// initializers for the `Update*`/`View*` struct literals, all keyed
+/// off the same per-field binding names so the two stay in sync.
+fn build_into_signals(
+  fields: &[ParsedField],
+) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
+  let mut bindings = Vec::new();
+  let mut update_ctor_fields = Vec::new();
+  let mut view_ctor_fields = Vec::new();
+
+  for field in fields {
+    let ident = &field.ident;
+    let update_binding = format_ident!("{}_update", ident);
+    let view_binding = format_ident!("{}_view", ident);
+
+    match field.kind {
+      ModelField::Plain => bindings.push(quote! {
+        let #ident = ::leptos_reactive::create_rw_signal(cx, self.#ident);
+        let #update_binding = #ident;
+        let #view_binding = #ident.read_only();
+      }),
+      ModelField::Nested | ModelField::Routed { .. } => bindings.push(quote! {
+        let (#update_binding, #view_binding) = self.#ident.into_signals(cx);
+      }),
+    }
+
+    update_ctor_fields.push(quote! { #ident: #update_binding });
+    view_ctor_fields.push(quote! { #ident: #view_binding });
+  }
+
+  (bindings, update_ctor_fields, view_ctor_fields)
+}